@@ -0,0 +1,8 @@
+//! Error handling for the prost-derive crate.
+//!
+//! `error_chain!` gives us an `Error`/`Result` pair along with a `bail!`
+//! macro and a blanket `From<String>` (and `From<&str>`) impl, which is all
+//! the attribute parsing in `field` needs.
+
+error_chain! {
+}
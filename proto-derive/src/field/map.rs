@@ -0,0 +1,81 @@
+use quote::Tokens;
+use syn::{
+    Ident,
+    MetaItem,
+};
+
+use field::{
+    Ctxt,
+    MISSING_TAG,
+    aliases_attr,
+    set_bool,
+    set_option,
+    tag_attr,
+    word_attr,
+};
+
+pub struct Field {
+    pub tag: u32,
+    pub aliases: Vec<u32>,
+}
+
+impl Field {
+    pub fn new(attrs: &[MetaItem], ctxt: &Ctxt) -> Option<Field> {
+        let mut map = false;
+        let mut tag = None;
+        let mut aliases = Vec::new();
+
+        for attr in attrs {
+            if word_attr("map", attr) {
+                set_bool(ctxt, &mut map, "duplicate map attributes");
+            } else if let Some(t) = tag_attr(ctxt, attr) {
+                set_option(ctxt, &mut tag, t, "duplicate tag attributes");
+            } else if let Some(a) = aliases_attr(ctxt, attr) {
+                aliases.extend(a);
+            }
+        }
+
+        if !map {
+            return None;
+        }
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                ctxt.error("missing tag attribute on map field");
+                MISSING_TAG
+            },
+        };
+
+        if aliases.contains(&tag) {
+            ctxt.error(format!("tag {} is both the primary tag and an alias", tag));
+        }
+
+        Some(Field {
+            tag: tag,
+            aliases: aliases,
+        })
+    }
+
+    /// Returns an expression which evaluates to the result of encoding the field.
+    pub fn encode(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        quote!(::prost::encoding::map::encode(#tag, &#ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the result of merging a
+    /// decoded map entry into the field.
+    pub fn merge(&self, ident: &Ident) -> Tokens {
+        quote!(::prost::encoding::map::merge(&mut #ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the encoded length of the field.
+    pub fn encoded_len(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        quote!(::prost::encoding::map::encoded_len(#tag, &#ident))
+    }
+
+    pub fn methods(&self, _ident: &Ident) -> Option<Tokens> {
+        None
+    }
+}
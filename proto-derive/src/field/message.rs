@@ -0,0 +1,83 @@
+use quote::Tokens;
+use syn::{
+    Ident,
+    MetaItem,
+};
+
+use field::{
+    Ctxt,
+    Label,
+    MISSING_TAG,
+    aliases_attr,
+    set_bool,
+    set_option,
+    tag_attr,
+    word_attr,
+};
+
+pub struct Field {
+    pub label: Label,
+    pub tag: u32,
+    pub aliases: Vec<u32>,
+}
+
+impl Field {
+    pub fn new(attrs: &[MetaItem], ctxt: &Ctxt) -> Option<Field> {
+        let mut message = false;
+        let mut label = None;
+        let mut tag = None;
+        let mut aliases = Vec::new();
+
+        for attr in attrs {
+            if word_attr("message", attr) {
+                set_bool(ctxt, &mut message, "duplicate message attributes");
+            } else if let Some(l) = Label::from_attr(attr) {
+                set_option(ctxt, &mut label, l, "duplicate label attributes");
+            } else if let Some(t) = tag_attr(ctxt, attr) {
+                set_option(ctxt, &mut tag, t, "duplicate tag attributes");
+            } else if let Some(a) = aliases_attr(ctxt, attr) {
+                aliases.extend(a);
+            }
+        }
+
+        if !message {
+            return None;
+        }
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                ctxt.error("missing tag attribute on message field");
+                MISSING_TAG
+            },
+        };
+
+        if aliases.contains(&tag) {
+            ctxt.error(format!("tag {} is both the primary tag and an alias", tag));
+        }
+
+        Some(Field {
+            label: label.unwrap_or(Label::Optional),
+            tag: tag,
+            aliases: aliases,
+        })
+    }
+
+    /// Returns an expression which evaluates to the result of encoding the field.
+    pub fn encode(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        quote!(::prost::encoding::message::encode(#tag, &#ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the result of merging a
+    /// decoded message into the field.
+    pub fn merge(&self, ident: &Ident) -> Tokens {
+        quote!(::prost::encoding::message::merge(&mut #ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the encoded length of the field.
+    pub fn encoded_len(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        quote!(::prost::encoding::message::encoded_len(#tag, &#ident))
+    }
+}
@@ -3,8 +3,12 @@ mod message;
 mod oneof;
 mod scalar;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
+use std::fmt::Display;
 use std::slice;
+use std::thread;
 
 use quote::Tokens;
 use syn::{
@@ -17,6 +21,52 @@ use syn::{
 
 use error::*;
 
+/// An accumulator for errors encountered while parsing the `#[proto(..)]`
+/// attributes of a single derive invocation.
+///
+/// Rather than aborting on the first malformed attribute, parsing functions
+/// record their complaints here and keep going on a best-effort basis, so
+/// that `check` can report every problem found in the input at once. This
+/// mirrors the `Ctxt` used by `serde_derive`.
+///
+/// `check` must be called exactly once, after all fields (and any other
+/// attributes) belonging to the derive have been parsed; dropping a `Ctxt`
+/// that still holds unchecked errors is a bug, and is caught by panicking
+/// in `drop`.
+pub struct Ctxt {
+    errors: RefCell<Vec<String>>,
+}
+
+impl Ctxt {
+    pub fn new() -> Ctxt {
+        Ctxt { errors: RefCell::new(Vec::new()) }
+    }
+
+    /// Records an error without aborting parsing.
+    pub fn error<T: Display>(&self, msg: T) {
+        self.errors.borrow_mut().push(msg.to_string());
+    }
+
+    /// Consumes the context, returning `Ok(())` if no errors were recorded,
+    /// or a single `Error` concatenating every recorded message otherwise.
+    pub fn check(self) -> Result<()> {
+        let errors = self.errors.borrow_mut().split_off(0);
+        match errors.len() {
+            0 => Ok(()),
+            1 => bail!("{}", errors[0]),
+            _ => bail!("{} errors:\n{}", errors.len(), errors.join("\n")),
+        }
+    }
+}
+
+impl Drop for Ctxt {
+    fn drop(&mut self) {
+        if !thread::panicking() && !self.errors.borrow().is_empty() {
+            panic!("forgot to check for errors: {:?}", self.errors.borrow());
+        }
+    }
+}
+
 pub enum Field {
     /// A scalar field.
     Scalar(scalar::Field),
@@ -26,49 +76,87 @@ pub enum Field {
     Map(map::Field),
     /// A oneof field.
     Oneof(oneof::Field),
+    /// A field excluded from the wire format entirely via `#[proto(skip)]`
+    /// (or `ignore`). It claims no tags and is never encoded, merged, or
+    /// exposed through generated methods, but it's still a real `Field` so
+    /// the whole message's `default()` keeps initializing it.
+    Ignored,
 }
 
 impl Field {
 
     /// Creates a new `Field` from an iterator of field attributes.
     ///
-    /// If the meta items are invalid, an error will be returned.
-    /// If the field should be ignored, `None` is returned.
-    pub fn new(attrs: Vec<Attribute>) -> Result<Option<Field>> {
+    /// Any malformed meta items are reported to `ctxt` rather than aborting;
+    /// the caller must call `ctxt.check()` once all fields of the derive
+    /// have been parsed.
+    ///
+    /// If no `#[proto(..)]` attribute matches any known field kind, `None`
+    /// is returned; a `#[proto(skip)]`/`#[proto(ignore)]` field instead
+    /// comes back as `Some(Field::Ignored)`.
+    pub fn new(attrs: Vec<Attribute>, ctxt: &Ctxt) -> Option<Field> {
         // Get the items belonging to the 'proto' list attribute (e.g. #[proto(foo, bar="baz")]).
         let attrs: Vec<MetaItem> = attrs.into_iter().flat_map(|attr| match attr.value {
             MetaItem::List(ident, items) => if ident == "proto" { items } else { Vec::new() },
             _ => Vec::new(),
-        }).flat_map(|attr| -> Result<_> {
+        }).flat_map(|attr| -> Option<_> {
             match attr {
-                NestedMetaItem::MetaItem(attr) => Ok(attr),
-                NestedMetaItem::Literal(lit) => bail!("invalid proto attribute: {:?}", lit),
+                NestedMetaItem::MetaItem(attr) => Some(attr),
+                NestedMetaItem::Literal(lit) => {
+                    ctxt.error(format!("invalid proto attribute: {:?}", lit));
+                    None
+                },
             }
         }).collect();
 
-        // TODO: check for ignore attribute.
+        // A field marked `#[proto(skip)]` (or the `ignore` alias) carries no
+        // wire representation at all, so it's excluded from tag assignment
+        // and never reaches the type-specific parsers below. It still comes
+        // back as `Field::Ignored` rather than `None`, so the message-level
+        // codegen that walks every field to build `default()` doesn't lose it.
+        if attrs.iter().any(|attr| word_attr("skip", attr) || word_attr("ignore", attr)) {
+            if attrs.iter().any(|attr| attr.name() == "tag" || attr.name() == "tags") {
+                ctxt.error("skip and tag attributes are mutually exclusive");
+            }
+            return Some(Field::Ignored);
+        }
 
-        let field = if let Some(field) = scalar::Field::new(&attrs)? {
-            Field::Scalar(field)
-        } else if let Some(field) = message::Field::new(&attrs)? {
-            Field::Message(field)
-        } else if let Some(field) = map::Field::new(&attrs)? {
-            Field::Map(field)
-        } else if let Some(field) = oneof::Field::new(&attrs)? {
-            Field::Oneof(field)
+        if let Some(field) = scalar::Field::new(&attrs, ctxt) {
+            Some(Field::Scalar(field))
+        } else if let Some(field) = message::Field::new(&attrs, ctxt) {
+            Some(Field::Message(field))
+        } else if let Some(field) = map::Field::new(&attrs, ctxt) {
+            Some(Field::Map(field))
+        } else if let Some(field) = oneof::Field::new(&attrs, ctxt) {
+            Some(Field::Oneof(field))
         } else {
-            bail!("no type attribute");
-        };
-
-        Ok(Some(field))
+            ctxt.error("no type attribute");
+            None
+        }
     }
 
+    /// Returns every tag that should route decoded wire values into this
+    /// field, i.e. the primary tag plus any `#[proto(aliases(..))]` tags.
+    /// `encode`/`encoded_len` only ever use the primary tag.
     pub fn tags(&self) -> Vec<u32> {
         match *self {
-            Field::Scalar(ref scalar) => vec![scalar.tag],
-            Field::Message(ref message) => vec![message.tag],
-            Field::Map(ref map) => vec![map.tag],
+            Field::Scalar(ref scalar) => {
+                let mut tags = vec![scalar.tag];
+                tags.extend_from_slice(&scalar.aliases);
+                tags
+            },
+            Field::Message(ref message) => {
+                let mut tags = vec![message.tag];
+                tags.extend_from_slice(&message.aliases);
+                tags
+            },
+            Field::Map(ref map) => {
+                let mut tags = vec![map.tag];
+                tags.extend_from_slice(&map.aliases);
+                tags
+            },
             Field::Oneof(ref oneof) => oneof.tags.clone(),
+            Field::Ignored => Vec::new(),
         }
     }
 
@@ -79,6 +167,7 @@ impl Field {
             Field::Message(ref message) => message.encode(ident),
             Field::Map(ref map) => map.encode(ident),
             Field::Oneof { .. } => quote!(();),
+            Field::Ignored => quote!(()),
         }
     }
 
@@ -106,7 +195,11 @@ impl Field {
     pub fn default(&self) -> Tokens {
         match *self {
             Field::Scalar(ref scalar) => scalar.default(),
-            _ => quote!(::std::default::Default::default()),
+            // Message/Map/Oneof fields fall back to their Rust type's own
+            // `Default` impl, and so does a `#[proto(skip)]` field -- spelled
+            // with a `::core` path here, although other parts of the
+            // generated code still name `::std` paths.
+            _ => quote!(::core::default::Default::default()),
         }
     }
 
@@ -119,6 +212,49 @@ impl Field {
     }
 }
 
+/// Sentinel tag stored by a sub-parser when a field is missing its `tag`
+/// attribute; the missing-tag error is reported at parse time, so
+/// `check_tags` skips it rather than also reporting it as out of range.
+const MISSING_TAG: u32 = 0;
+/// Smallest valid protobuf field tag.
+const MIN_TAG: u32 = 1;
+/// Largest valid protobuf field tag.
+const MAX_TAG: u32 = 536_870_911;
+/// Reserved tag range that protobuf implementations use for internal
+/// bookkeeping; messages may not assign it to a field.
+const RESERVED_TAG_MIN: u32 = 19_000;
+const RESERVED_TAG_MAX: u32 = 19_999;
+
+/// Checks that every tag claimed by `fields` (including aliases) is within
+/// the valid protobuf range and that no two fields claim the same tag.
+///
+/// Every collision and out-of-range tag is reported through `ctxt`, so a
+/// message with several conflicts reports all of them from a single
+/// compile, rather than just the first one found.
+pub fn check_tags(fields: &[(Ident, Field)], ctxt: &Ctxt) {
+    let mut tags: HashMap<u32, Ident> = HashMap::new();
+    for &(ref field_ident, ref field) in fields {
+        for tag in field.tags() {
+            // A field with no `tag` attribute at all is stored with this
+            // sentinel by its sub-parser, which has already reported the
+            // missing-tag error; don't pile a second "invalid tag 0"
+            // diagnostic for the same field on top of it.
+            if tag == MISSING_TAG {
+                continue;
+            }
+            if tag < MIN_TAG || tag > MAX_TAG ||
+               (tag >= RESERVED_TAG_MIN && tag <= RESERVED_TAG_MAX) {
+                ctxt.error(format!("invalid tag {} on field `{}`: tags must be in the range 1..=536870911, excluding 19000..=19999",
+                                    tag, field_ident));
+                continue;
+            }
+            if let Some(existing) = tags.insert(tag, field_ident.clone()) {
+                ctxt.error(format!("tag {} used by both `{}` and `{}`", tag, existing, field_ident));
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Label {
     /// An optional field.
@@ -173,107 +309,161 @@ impl fmt::Display for Label {
     }
 }
 
-pub fn set_option<T>(option: &mut Option<T>, value: T, message: &str) -> Result<()>
+pub fn set_option<T>(ctxt: &Ctxt, option: &mut Option<T>, value: T, message: &str)
 where T: fmt::Debug {
     if let Some(ref existing) = *option {
-        bail!("{}: {:?} and {:?}", message, existing, value);
+        ctxt.error(format!("{}: {:?} and {:?}", message, existing, value));
+    } else {
+        *option = Some(value);
     }
-    *option = Some(value);
-    Ok(())
 }
 
-pub fn set_bool(b: &mut bool, message: &str) -> Result<()> {
+pub fn set_bool(ctxt: &Ctxt, b: &mut bool, message: &str) {
     if *b {
-        bail!(message);
+        ctxt.error(message);
     } else {
         *b = true;
-        Ok(())
     }
 }
 
 
-/// Unpacks an attribute into a (key, boolean) pair, returning the boolean value.
-/// If the key doesn't match the attribute, `None` is returned.
-fn bool_attr(key: &str, attr: &MetaItem) -> Result<Option<bool>> {
-    if attr.name() != key {
-        return Ok(None);
+/// Checks if an attribute matches a word.
+fn word_attr(key: &str, attr: &MetaItem) -> bool {
+    if let MetaItem::Word(ref ident) = *attr {
+        ident == key
+    } else {
+        false
+    }
+}
+
+fn tag_attr(ctxt: &Ctxt, attr: &MetaItem) -> Option<u32> {
+    if attr.name() != "tag" {
+        return None;
     }
     match *attr {
-        MetaItem::Word(..) => Ok(Some(true)),
         MetaItem::List(_, ref items) => {
             // TODO(rustlang/rust#23121): slice pattern matching would make this much nicer.
             if items.len() == 1 {
-                if let NestedMetaItem::Literal(Lit::Bool(value)) = items[0] {
-                    return Ok(Some(value))
+                if let NestedMetaItem::Literal(Lit::Int(value, _)) = items[0] {
+                    return Some(value as u32);
                 }
             }
-            bail!("invalid {} attribute", key);
+            ctxt.error(format!("invalid tag attribute: {:?}", attr));
+            None
         },
-        MetaItem::NameValue(_, Lit::Str(ref s, _)) => {
-            s.parse::<bool>().map_err(|e| Error::from(e.to_string())).map(Option::Some)
+        MetaItem::NameValue(_, ref lit) => {
+            match *lit {
+                Lit::Str(ref s, _) => match s.parse::<u32>() {
+                    Ok(value) => Some(value),
+                    Err(e) => {
+                        ctxt.error(format!("invalid tag attribute: {}", e));
+                        None
+                    },
+                },
+                Lit::Int(value, _) => Some(value as u32),
+                _ => {
+                    ctxt.error(format!("invalid tag attribute: {:?}", attr));
+                    None
+                },
+            }
+        },
+        _ => {
+            ctxt.error(format!("invalid tag attribute: {:?}", attr));
+            None
         },
-        MetaItem::NameValue(_, Lit::Bool(value)) => Ok(Some(value)),
-        _ => bail!("invalid {} attribute", key),
     }
 }
 
-/// Checks if an attribute matches a word.
-fn word_attr(key: &str, attr: &MetaItem) -> bool {
-    if let MetaItem::Word(ref ident) = *attr {
-        ident == key
-    } else {
-        false
+/// Unpacks an `aliases` attribute into the list of secondary tags that
+/// should decode into the field, alongside its primary `tag`
+/// (e.g. `#[proto(tag = 5, aliases(2, 3))]`).
+/// If the key doesn't match the attribute, `None` is returned.
+fn aliases_attr(ctxt: &Ctxt, attr: &MetaItem) -> Option<Vec<u32>> {
+    if attr.name() != "aliases" && attr.name() != "alias" {
+        return None;
+    }
+    match *attr {
+        MetaItem::List(_, ref items) => {
+            let mut aliases = Vec::with_capacity(items.len());
+            for item in items {
+                if let NestedMetaItem::Literal(Lit::Int(value, _)) = *item {
+                    aliases.push(value as u32);
+                } else {
+                    ctxt.error(format!("invalid aliases attribute: {:?}", attr));
+                    return None;
+                }
+            }
+            Some(aliases)
+        },
+        _ => {
+            ctxt.error(format!("invalid aliases attribute: {:?}", attr));
+            None
+        },
     }
 }
 
-fn tag_attr(attr: &MetaItem) -> Result<Option<u32>> {
-    if attr.name() != "tag" {
-        return Ok(None);
+/// Unpacks a `default` attribute into the literal it was given.
+/// If the key doesn't match the attribute, `None` is returned.
+///
+/// Per RFC 1559, any literal is accepted here (int, float, bool, string, or
+/// byte-string); it's up to the caller to reject literals that don't make
+/// sense for the field's type.
+fn default_attr(ctxt: &Ctxt, attr: &MetaItem) -> Option<Lit> {
+    if attr.name() != "default" {
+        return None;
     }
     match *attr {
+        MetaItem::NameValue(_, ref lit) => Some(lit.clone()),
         MetaItem::List(_, ref items) => {
             // TODO(rustlang/rust#23121): slice pattern matching would make this much nicer.
             if items.len() == 1 {
-                if let NestedMetaItem::Literal(Lit::Int(value, _)) = items[0] {
-                    return Ok(Some(value as u32));
+                if let NestedMetaItem::Literal(ref lit) = items[0] {
+                    return Some(lit.clone());
                 }
             }
-            bail!("invalid tag attribute: {:?}", attr);
+            ctxt.error(format!("invalid default attribute: {:?}", attr));
+            None
         },
-        MetaItem::NameValue(_, ref lit) => {
-            match *lit {
-                Lit::Str(ref s, _) => s.parse::<u32>().map_err(|e| Error::from(e.to_string()))
-                                                      .map(Option::Some),
-                Lit::Int(value, _) => return Ok(Some(value as u32)),
-                _ => bail!("invalid tag attribute: {:?}", attr),
-            }
+        _ => {
+            ctxt.error(format!("invalid default attribute: {:?}", attr));
+            None
         },
-        _ => bail!("invalid tag attribute: {:?}", attr),
     }
 }
 
-fn tags_attr(attr: &MetaItem) -> Result<Option<Vec<u32>>> {
+fn tags_attr(ctxt: &Ctxt, attr: &MetaItem) -> Option<Vec<u32>> {
     if attr.name() != "tags" {
-        return Ok(None);
+        return None;
     }
     match *attr {
         MetaItem::List(_, ref items) => {
             let mut tags = Vec::with_capacity(items.len());
             for item in items {
-                if let Some(&NestedMetaItem::Literal(Lit::Int(value, _))) = items.first() {
+                if let NestedMetaItem::Literal(Lit::Int(value, _)) = *item {
                     tags.push(value as u32);
                 } else {
-                    bail!("invalid tag attribute: {:?}", attr);
+                    ctxt.error(format!("invalid tag attribute: {:?}", attr));
+                    return None;
                 }
             }
-            return Ok(Some(tags));
+            Some(tags)
         },
         MetaItem::NameValue(_, Lit::Str(ref s, _)) => {
-            s.split(',')
-             .map(|s| s.trim().parse::<u32>().map_err(|e| Error::from(e.to_string())))
-             .collect::<Result<Vec<u32>>>()
-             .map(|tags| Some(tags))
+            let mut tags = Vec::new();
+            for s in s.split(',') {
+                match s.trim().parse::<u32>() {
+                    Ok(tag) => tags.push(tag),
+                    Err(e) => {
+                        ctxt.error(format!("invalid tag attribute: {}", e));
+                        return None;
+                    },
+                }
+            }
+            Some(tags)
+        },
+        _ => {
+            ctxt.error(format!("invalid tag attribute: {:?}", attr));
+            None
         },
-        _ => bail!("invalid tag attribute: {:?}", attr),
     }
 }
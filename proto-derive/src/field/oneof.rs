@@ -0,0 +1,42 @@
+use syn::MetaItem;
+
+use field::{
+    Ctxt,
+    set_bool,
+    set_option,
+    tags_attr,
+    word_attr,
+};
+
+pub struct Field {
+    pub tags: Vec<u32>,
+}
+
+impl Field {
+    pub fn new(attrs: &[MetaItem], ctxt: &Ctxt) -> Option<Field> {
+        let mut oneof = false;
+        let mut tags = None;
+
+        for attr in attrs {
+            if word_attr("oneof", attr) {
+                set_bool(ctxt, &mut oneof, "duplicate oneof attributes");
+            } else if let Some(t) = tags_attr(ctxt, attr) {
+                set_option(ctxt, &mut tags, t, "duplicate tags attributes");
+            }
+        }
+
+        if !oneof {
+            return None;
+        }
+
+        let tags = match tags {
+            Some(tags) => tags,
+            None => {
+                ctxt.error("oneof field is missing a tags attribute");
+                Vec::new()
+            },
+        };
+
+        Some(Field { tags: tags })
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+use std::slice;
+
+use quote::Tokens;
+use syn::{
+    Ident,
+    Lit,
+    MetaItem,
+};
+
+use field::{
+    Ctxt,
+    Label,
+    MISSING_TAG,
+    default_attr,
+    aliases_attr,
+    set_option,
+    tag_attr,
+};
+
+/// A scalar protobuf field type.
+///
+/// Protobuf `enum` fields are out of scope here: this tree has no
+/// `#[derive(Enumeration)]` (or equivalent) to resolve an enum default like
+/// `default = FOO` to a variant path against, so there's no `Ty::Enum`
+/// variant and `default_value` below only handles the literal defaults
+/// (int/float/bool/string/bytes) that apply to the types listed. Adding
+/// enum-variant defaults belongs with whatever introduces enum field
+/// support, not here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ty {
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Float,
+    Double,
+    Bool,
+    String,
+    Bytes,
+}
+
+impl Ty {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Ty::Int32 => "int32",
+            Ty::Int64 => "int64",
+            Ty::Uint32 => "uint32",
+            Ty::Uint64 => "uint64",
+            Ty::Sint32 => "sint32",
+            Ty::Sint64 => "sint64",
+            Ty::Fixed32 => "fixed32",
+            Ty::Fixed64 => "fixed64",
+            Ty::Sfixed32 => "sfixed32",
+            Ty::Sfixed64 => "sfixed64",
+            Ty::Float => "float",
+            Ty::Double => "double",
+            Ty::Bool => "bool",
+            Ty::String => "string",
+            Ty::Bytes => "bytes",
+        }
+    }
+
+    fn variants() -> slice::Iter<'static, Ty> {
+        const VARIANTS: &'static [Ty] = &[
+            Ty::Int32,
+            Ty::Int64,
+            Ty::Uint32,
+            Ty::Uint64,
+            Ty::Sint32,
+            Ty::Sint64,
+            Ty::Fixed32,
+            Ty::Fixed64,
+            Ty::Sfixed32,
+            Ty::Sfixed64,
+            Ty::Float,
+            Ty::Double,
+            Ty::Bool,
+            Ty::String,
+            Ty::Bytes,
+        ];
+        VARIANTS.iter()
+    }
+
+    /// Parses a meta item into a scalar type.
+    /// If the meta item doesn't match a scalar type, `None` is returned.
+    fn from_attr(attr: &MetaItem) -> Option<Ty> {
+        if let MetaItem::Word(ref ident) = *attr {
+            for &ty in Ty::variants() {
+                if ident == ty.as_str() {
+                    return Some(ty);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether a string or byte-string literal is a valid default for this type.
+    fn accepts_str_default(&self) -> bool {
+        match *self {
+            Ty::String | Ty::Bytes => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub struct Field {
+    pub ty: Ty,
+    pub label: Label,
+    pub tag: u32,
+    pub aliases: Vec<u32>,
+    pub default: Option<Lit>,
+}
+
+impl Field {
+    pub fn new(attrs: &[MetaItem], ctxt: &Ctxt) -> Option<Field> {
+        let mut ty = None;
+        let mut label = None;
+        let mut tag = None;
+        let mut aliases = Vec::new();
+        let mut default = None;
+
+        for attr in attrs {
+            if let Some(t) = Ty::from_attr(attr) {
+                set_option(ctxt, &mut ty, t, "duplicate type attributes");
+            } else if let Some(l) = Label::from_attr(attr) {
+                set_option(ctxt, &mut label, l, "duplicate label attributes");
+            } else if let Some(t) = tag_attr(ctxt, attr) {
+                set_option(ctxt, &mut tag, t, "duplicate tag attributes");
+            } else if let Some(a) = aliases_attr(ctxt, attr) {
+                aliases.extend(a);
+            } else if let Some(d) = default_attr(ctxt, attr) {
+                set_option(ctxt, &mut default, d, "duplicate default attributes");
+            }
+        }
+
+        let ty = match ty {
+            Some(ty) => ty,
+            None => return None,
+        };
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                ctxt.error(format!("missing tag attribute on {} field", ty));
+                MISSING_TAG
+            },
+        };
+
+        if let Some(ref default) = default {
+            let valid = match *default {
+                Lit::Str(..) => ty == Ty::String,
+                Lit::ByteStr(..) => ty == Ty::Bytes,
+                Lit::Bool(..) => ty == Ty::Bool,
+                // An int literal can seed either an integer or a floating-point
+                // default (`default = 1` on a `float` field), but a float
+                // literal would silently truncate if accepted for an integer
+                // field, so it's restricted to float/double.
+                Lit::Int(..) => !ty.accepts_str_default() && ty != Ty::Bool,
+                Lit::Float(..) => ty == Ty::Float || ty == Ty::Double,
+                _ => false,
+            };
+            if !valid {
+                ctxt.error(format!("invalid default value for {} field: {:?}", ty, default));
+            }
+        }
+
+        if aliases.contains(&tag) {
+            ctxt.error(format!("tag {} is both the primary tag and an alias", tag));
+        }
+
+        Some(Field {
+            ty: ty,
+            label: label.unwrap_or(Label::Optional),
+            tag: tag,
+            aliases: aliases,
+            default: default,
+        })
+    }
+
+    /// Returns an expression which evaluates to the result of encoding the field.
+    pub fn encode(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        let encode_fn = Ident::new(format!("encode_{}", self.ty));
+        quote!(::prost::encoding::#encode_fn(#tag, &#ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the result of merging a
+    /// decoded scalar value into the field.
+    pub fn merge(&self, ident: &Ident) -> Tokens {
+        let merge_fn = Ident::new(format!("merge_{}", self.ty));
+        quote!(::prost::encoding::#merge_fn(wire_type, &mut #ident, buf))
+    }
+
+    /// Returns an expression which evaluates to the encoded length of the field.
+    pub fn encoded_len(&self, ident: &Ident) -> Tokens {
+        let tag = self.tag;
+        let encoded_len_fn = Ident::new(format!("encoded_len_{}", self.ty));
+        quote!(::prost::encoding::#encoded_len_fn(#tag, &#ident))
+    }
+
+    /// Returns an expression which evaluates to this field's default value,
+    /// honoring an explicit `#[proto(default = ...)]` literal when given.
+    pub fn default(&self) -> Tokens {
+        match self.default {
+            Some(ref lit) => default_value(self.ty, lit),
+            None => quote!(::core::default::Default::default()),
+        }
+    }
+
+    /// For optional scalar fields, returns a getter which falls back to the
+    /// field's default value (the configured one, or the type's zero value)
+    /// when the field is unset, matching proto2 semantics.
+    pub fn methods(&self, ident: &Ident) -> Option<Tokens> {
+        if self.label != Label::Optional {
+            return None;
+        }
+        let rust_ty = rust_type(self.ty);
+        let default = self.default();
+        Some(quote! {
+            pub fn #ident(&self) -> #rust_ty {
+                match self.#ident {
+                    ::std::option::Option::Some(ref value) => value.clone(),
+                    ::std::option::Option::None => #default,
+                }
+            }
+        })
+    }
+}
+
+/// The native Rust type used to represent a scalar of this protobuf type.
+fn rust_type(ty: Ty) -> Tokens {
+    match ty {
+        Ty::Int32 | Ty::Sint32 | Ty::Sfixed32 => quote!(i32),
+        Ty::Int64 | Ty::Sint64 | Ty::Sfixed64 => quote!(i64),
+        Ty::Uint32 | Ty::Fixed32 => quote!(u32),
+        Ty::Uint64 | Ty::Fixed64 => quote!(u64),
+        Ty::Float => quote!(f32),
+        Ty::Double => quote!(f64),
+        Ty::Bool => quote!(bool),
+        Ty::String => quote!(String),
+        Ty::Bytes => quote!(Vec<u8>),
+    }
+}
+
+fn default_value(ty: Ty, lit: &Lit) -> Tokens {
+    match *lit {
+        Lit::Str(ref s, _) => quote!(#s.to_string()),
+        Lit::ByteStr(ref bytes, _) => quote!(vec![#(#bytes),*]),
+        Lit::Int(value, _) => {
+            let rust_ty = rust_type(ty);
+            quote!(#value as #rust_ty)
+        },
+        Lit::Float(ref s, _) => {
+            let value: f64 = s.parse().unwrap_or(0.0);
+            let rust_ty = rust_type(ty);
+            quote!(#value as #rust_ty)
+        },
+        Lit::Bool(value) => quote!(#value),
+        _ => quote!(::core::default::Default::default()),
+    }
+}
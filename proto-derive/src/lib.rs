@@ -0,0 +1,123 @@
+#![recursion_limit = "128"]
+
+extern crate proc_macro;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+#[macro_use]
+extern crate error_chain;
+
+mod error;
+mod field;
+
+use proc_macro::TokenStream;
+use quote::Tokens;
+use syn::{
+    Body,
+    Ident,
+    MacroInput,
+    VariantData,
+};
+
+use error::*;
+use field::{Ctxt, Field};
+
+#[proc_macro_derive(Message, attributes(proto))]
+pub fn message(input: TokenStream) -> TokenStream {
+    let source = input.to_string();
+    let ast = syn::parse_macro_input(&source).expect("unable to parse message type");
+    let expanded = try_message(ast).unwrap();
+    expanded.to_string().parse().expect("unable to parse expanded derive output")
+}
+
+fn try_message(ast: MacroInput) -> Result<Tokens> {
+    let ident = ast.ident;
+
+    let variant_data = match ast.body {
+        Body::Struct(variant_data) => variant_data,
+        Body::Enum(..) => bail!("Message can not be derived for an enum"),
+    };
+
+    // `Ctxt` is created once per derive invocation and threaded through
+    // every field's attribute parsing, so a struct with several malformed
+    // `#[proto(..)]` attributes reports all of them from a single compile.
+    let ctxt = Ctxt::new();
+
+    let fields: Vec<(Ident, Field)> = match variant_data {
+        VariantData::Struct(fields) | VariantData::Tuple(fields) => {
+            fields.into_iter().enumerate().filter_map(|(idx, field)| {
+                let field_ident = field.ident.unwrap_or_else(|| Ident::new(format!("__field{}", idx)));
+                Field::new(field.attrs, &ctxt).map(|field| (field_ident, field))
+            }).collect()
+        },
+        VariantData::Unit => Vec::new(),
+    };
+
+    // Cross-check every field's tags (including aliases) for collisions and
+    // out-of-range values before anything is generated from them.
+    field::check_tags(&fields, &ctxt);
+
+    // Drains and reports every error accumulated above as a single compile
+    // failure; `Ctxt::drop` would otherwise panic if this were skipped
+    // while errors are pending.
+    ctxt.check()?;
+
+    let encoded_len = fields.iter().map(|&(ref ident, ref field)| {
+        field.encoded_len(ident)
+    });
+
+    let encode = fields.iter().map(|&(ref ident, ref field)| {
+        field.encode(ident)
+    });
+
+    let merge = fields.iter().flat_map(|&(ref ident, ref field)| {
+        field.tags().into_iter().map(move |tag| {
+            let merge = field.merge(ident);
+            quote!(#tag => #merge)
+        })
+    });
+
+    let default = fields.iter().map(|&(ref ident, ref field)| {
+        let default = field.default();
+        quote!(#ident: #default)
+    });
+
+    let methods = fields.iter().filter_map(|&(ref ident, ref field)| {
+        field.methods(ident)
+    });
+
+    Ok(quote! {
+        impl ::prost::Message for #ident {
+            #[allow(unused_variables)]
+            fn encode_raw<B>(&self, buf: &mut B) where B: ::prost::bytes::BufMut {
+                #(#encode;)*
+            }
+
+            #[allow(unused_variables)]
+            fn merge_field<B>(&mut self, tag: u32, wire_type: ::prost::encoding::WireType, buf: &mut B)
+            -> ::std::result::Result<(), ::prost::DecodeError> where B: ::prost::bytes::Buf {
+                match tag {
+                    #(#merge,)*
+                    _ => ::prost::encoding::skip_field(wire_type, buf),
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn encoded_len(&self) -> usize {
+                0 #(+ #encoded_len)*
+            }
+        }
+
+        impl ::core::default::Default for #ident {
+            fn default() -> #ident {
+                #ident {
+                    #(#default,)*
+                }
+            }
+        }
+
+        impl #ident {
+            #(#methods)*
+        }
+    })
+}